@@ -0,0 +1,189 @@
+use std::fs::File;
+use std::io;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::os::unix::fs::FileExt;
+
+use bytes::BytesMut;
+use memmap2::Mmap;
+
+use crate::Result;
+
+/// A source of the raw bytes backing a [`Workspace`](crate::client::Workspace)'s data file.
+///
+/// Unlike `Read + Seek`, `read_at` takes `&self`, so concurrent reads of different files don't
+/// have to fight over a single shared seek cursor.
+pub trait BlockRead {
+
+    /// Reads `buf.len()` bytes starting at `offset` into `buf`.
+    ///
+    /// # Arguments
+    /// * `offset` - The offset, in bytes, to start reading from.
+    /// * `buf`    - The buffer to fill.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()>;
+
+    /// The total number of bytes available to read from this source.
+    fn len(&self) -> Result<u64>;
+
+    /// Whether this source has no bytes available to read.
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+impl BlockRead for File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        Ok(FileExt::read_exact_at(self, buf, offset)?)
+    }
+
+    fn len(&self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+impl BlockRead for Cursor<Vec<u8>> {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        slice_at(self.get_ref(), offset, buf)
+    }
+
+    fn len(&self) -> Result<u64> {
+        Ok(self.get_ref().len() as u64)
+    }
+}
+
+impl BlockRead for BytesMut {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        slice_at(self, offset, buf)
+    }
+
+    fn len(&self) -> Result<u64> {
+        Ok(BytesMut::len(self) as u64)
+    }
+}
+
+impl BlockRead for Mmap {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        slice_at(self, offset, buf)
+    }
+
+    fn len(&self) -> Result<u64> {
+        Ok(self.as_ref().len() as u64)
+    }
+}
+
+/// A bounded, streaming view over a single entry in a [`BlockRead`] source, clamped to
+/// `[start, start + length)`. Unlike [`Workspace::data`](crate::client::Workspace::data), this
+/// never buffers the whole entry up-front - bytes (and the bounds check that guards them) are
+/// only pulled from the source as the caller reads.
+pub struct BoundedReader<'a, B: BlockRead> {
+    source: &'a B,
+    start: u64,
+    end: u64,
+    position: u64
+}
+
+impl<'a, B: BlockRead> BoundedReader<'a, B> {
+    /// Creates a reader bounded to `[start, start + length)` of `source`.
+    pub(crate) fn new(source: &'a B, start: u64, length: u64) -> BoundedReader<'a, B> {
+        BoundedReader { source, start, end: start + length, position: start }
+    }
+}
+
+impl<'a, B: BlockRead> Read for BoundedReader<'a, B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.end.saturating_sub(self.position);
+        let to_read = (buf.len() as u64).min(remaining) as usize;
+        if to_read == 0 {
+            return Ok(0);
+        }
+
+        self.source.read_at(self.position, &mut buf[..to_read])
+            .map_err(io::Error::other)?;
+        self.position += to_read as u64;
+        Ok(to_read)
+    }
+}
+
+impl<'a, B: BlockRead> Seek for BoundedReader<'a, B> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(offset) => self.start as i64 + offset as i64,
+            SeekFrom::End(offset) => self.end as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset
+        };
+
+        if new_position < self.start as i64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a position before the start of the entry"));
+        }
+
+        self.position = new_position as u64;
+        Ok(self.position - self.start)
+    }
+}
+
+/// Copies `buf.len()` bytes out of `data` starting at `offset`, bounds-checking first.
+fn slice_at(data: &[u8], offset: u64, buf: &mut [u8]) -> Result<()> {
+    let offset = offset as usize;
+    let required = offset + buf.len();
+    if required > data.len() {
+        return Err(format!("Required data exceeds the data available (required: {}, available: {})", required, data.len()).into());
+    }
+
+    buf.copy_from_slice(&data[offset..required]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::client::{Compression, SFile, SFolder, Workspace};
+
+    /// Test that a workspace backed by an in-memory `Cursor` reads the same bytes a `File`-backed
+    /// one would, without needing an actual SAH/SAF pair on disk.
+    #[test]
+    fn test_cursor_backed_workspace() {
+        let data = Cursor::new(b"hello world".to_vec());
+        let file = SFile { name: "greeting.txt".to_owned(), offset: 6, length: 5, original_length: 5, compression: Compression::None, hash: None };
+        let root = SFolder { name: "data".to_owned(), files: vec![file], folders: vec![] };
+
+        let workspace = Workspace::from_source(root, data);
+        let file = workspace.file("greeting.txt").unwrap();
+        assert_eq!(workspace.data(file).unwrap().as_ref(), b"world");
+    }
+
+    /// Test that `Workspace::reader` streams only the bytes within a file's bounds.
+    #[test]
+    fn test_bounded_reader() {
+        use std::io::Read;
+
+        let data = Cursor::new(b"hello world".to_vec());
+        let file = SFile { name: "greeting.txt".to_owned(), offset: 6, length: 5, original_length: 5, compression: Compression::None, hash: None };
+        let root = SFolder { name: "data".to_owned(), files: vec![file], folders: vec![] };
+
+        let workspace = Workspace::from_source(root, data);
+        let file = workspace.file("greeting.txt").unwrap();
+
+        let mut reader = workspace.reader(file);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"world");
+    }
+
+    /// Test that reading past the end of an out-of-bounds entry returns an `io::Error` instead of
+    /// panicking, since a corrupt or malicious header could index past the end of the data file.
+    #[test]
+    fn test_bounded_reader_rejects_out_of_bounds_entry() {
+        use std::io::Read;
+
+        let data = Cursor::new(b"hello world".to_vec());
+        let file = SFile { name: "greeting.txt".to_owned(), offset: 6, length: 100, original_length: 100, compression: Compression::None, hash: None };
+        let root = SFolder { name: "data".to_owned(), files: vec![file], folders: vec![] };
+
+        let workspace = Workspace::from_source(root, data);
+        let file = workspace.file("greeting.txt").unwrap();
+
+        let mut reader = workspace.reader(file);
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).is_err());
+    }
+}
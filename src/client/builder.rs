@@ -0,0 +1,350 @@
+use std::fs::File;
+use std::io::Write;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use ed25519_dalek::{Signer, SigningKey};
+
+use crate::client::{Compression, HEADER_MAGIC_VALUE};
+use crate::Result;
+
+/// Files at or above this size are compressed by the writer; smaller files aren't worth the
+/// overhead of a compression stream.
+const COMPRESSION_THRESHOLD: usize = 4096;
+
+/// A file that is staged in a [`WorkspaceBuilder`], awaiting being written out to a data file.
+struct PendingFile {
+    name: String,
+    data: Vec<u8>
+}
+
+/// A folder that is staged in a [`WorkspaceBuilder`], awaiting being written out to a header file.
+struct PendingFolder {
+    name: String,
+    files: Vec<PendingFile>,
+    folders: Vec<PendingFolder>
+}
+
+impl PendingFolder {
+    fn new(name: &str) -> PendingFolder {
+        PendingFolder { name: name.to_owned(), files: vec![], folders: vec![] }
+    }
+}
+
+/// Builds a new SAH/SAF archive pair from scratch, mirroring the folder tree that
+/// [`Workspace::from_archive`](crate::client::Workspace::from_archive) reads back.
+pub struct WorkspaceBuilder {
+    root: PendingFolder
+}
+
+impl Default for WorkspaceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkspaceBuilder {
+
+    /// Creates an empty builder.
+    pub fn new() -> WorkspaceBuilder {
+        WorkspaceBuilder { root: PendingFolder::new("data") }
+    }
+
+    /// Stages a file at a given path, creating any missing parent directories.
+    ///
+    /// # Arguments
+    /// * `path` - The path, relative to the workspace root, to add the file at.
+    /// * `data` - The contents of the file.
+    pub fn add_file(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        let (dir, name) = split_path(path);
+        let folder = self.mkdir(dir);
+        folder.files.push(PendingFile { name: name.to_owned(), data: data.to_owned() });
+        Ok(())
+    }
+
+    /// Stages an (empty) directory at a given path, creating any missing parent directories.
+    ///
+    /// # Arguments
+    /// * `path` - The path, relative to the workspace root, to create the directory at.
+    pub fn add_dir(&mut self, path: &str) -> Result<()> {
+        self.mkdir(path);
+        Ok(())
+    }
+
+    /// Finds, or creates, every folder along `path`, returning the final one.
+    fn mkdir(&mut self, path: &str) -> &mut PendingFolder {
+        let mut folder = &mut self.root;
+        for part in path.split('/').filter(|part| !part.is_empty()) {
+            let index = match folder.folders.iter().position(|f| f.name.eq_ignore_ascii_case(part)) {
+                Some(index) => index,
+                None => {
+                    folder.folders.push(PendingFolder::new(part));
+                    folder.folders.len() - 1
+                }
+            };
+            folder = &mut folder.folders[index];
+        }
+        folder
+    }
+
+    /// Streams the staged files into the data file, then writes the header that indexes them,
+    /// producing a valid SAH/SAF pair that [`Workspace::from_archive`](crate::client::Workspace::from_archive)
+    /// can load.
+    ///
+    /// # Arguments
+    /// * `header_path` - The path to write the Shaiya Archive Header to (usually "data.sah").
+    /// * `data_path`   - The path to write the Shaiya Archive File to (usually "data.saf").
+    pub fn finish(self, header_path: &str, data_path: &str) -> Result<()> {
+        let mut data_file = File::create(data_path)?;
+        let mut offset: u64 = 0;
+        let total_file_count = count_files(&self.root);
+        let indexed_root = write_bodies(&self.root, &mut data_file, &mut offset)?;
+
+        let mut header_file = File::create(header_path)?;
+        header_file.write_all(HEADER_MAGIC_VALUE.as_bytes())?;
+        header_file.write_all(&[0u8; 4])?;
+        header_file.write_i32::<LittleEndian>(total_file_count as i32)?;
+        header_file.write_all(&[0u8; 45])?;
+        write_folder(&indexed_root, &mut header_file)?;
+        Ok(())
+    }
+
+    /// Like [`WorkspaceBuilder::finish`], but also signs the written header with `signing_key` and
+    /// writes the detached signature to `<header_path>.sig`, ready for
+    /// [`Workspace::verify_header`](crate::client::Workspace::verify_header) to check.
+    ///
+    /// # Arguments
+    /// * `header_path` - The path to write the Shaiya Archive Header to (usually "data.sah").
+    /// * `data_path`   - The path to write the Shaiya Archive File to (usually "data.saf").
+    /// * `signing_key` - The Ed25519 key to sign the header with.
+    pub fn finish_signed(self, header_path: &str, data_path: &str, signing_key: &SigningKey) -> Result<()> {
+        self.finish(header_path, data_path)?;
+
+        let header_bytes = std::fs::read(header_path)?;
+        let signature = signing_key.sign(&header_bytes);
+
+        let signature_path = format!("{}.sig", header_path);
+        File::create(signature_path)?.write_all(&signature.to_bytes())?;
+        Ok(())
+    }
+}
+
+/// An indexed file, ready to be written into a header once its offset in the data file is known.
+struct IndexedFile {
+    name: String,
+    offset: u64,
+    length: u32,
+    original_length: u32,
+    compression: Compression,
+    hash: [u8; 32]
+}
+
+/// An indexed folder tree, ready to be written into a header.
+struct IndexedFolder {
+    name: String,
+    files: Vec<IndexedFile>,
+    folders: Vec<IndexedFolder>
+}
+
+/// Counts the total number of files in a folder, recursing into subfolders.
+fn count_files(folder: &PendingFolder) -> usize {
+    folder.files.len() + folder.folders.iter().map(count_files).sum::<usize>()
+}
+
+/// Streams every file in a folder tree into the data file, recording each file's offset and
+/// length as it goes.
+fn write_bodies(folder: &PendingFolder, data_file: &mut File, offset: &mut u64) -> Result<IndexedFolder> {
+    let mut files = Vec::with_capacity(folder.files.len());
+    for file in &folder.files {
+        let compression = if file.data.len() >= COMPRESSION_THRESHOLD { Compression::Deflate } else { Compression::None };
+        let stored = compression.compress(&file.data)?;
+
+        data_file.write_all(&stored)?;
+        files.push(IndexedFile {
+            name: file.name.clone(),
+            offset: *offset,
+            length: stored.len() as u32,
+            original_length: file.data.len() as u32,
+            compression,
+            hash: *blake3::hash(&file.data).as_bytes()
+        });
+        *offset += stored.len() as u64;
+    }
+
+    let mut folders = Vec::with_capacity(folder.folders.len());
+    for subfolder in &folder.folders {
+        let mut indexed = write_bodies(subfolder, data_file, offset)?;
+        indexed.name = subfolder.name.clone();
+        folders.push(indexed);
+    }
+
+    Ok(IndexedFolder { name: folder.name.clone(), files, folders })
+}
+
+/// Writes an indexed folder's file and subfolder records into a header file, mirroring the layout
+/// `parse_folder` expects to read.
+fn write_folder(folder: &IndexedFolder, header_file: &mut File) -> Result<()> {
+    header_file.write_i32::<LittleEndian>(folder.files.len() as i32)?;
+    for file in &folder.files {
+        header_file.write_i32::<LittleEndian>(file.name.len() as i32)?;
+        header_file.write_all(file.name.as_bytes())?;
+        header_file.write_all(&file.offset.to_le_bytes())?;
+        header_file.write_all(&file.length.to_le_bytes())?;
+        header_file.write_u8(file.compression.flag())?;
+        header_file.write_all(&[0u8; 3])?;
+        header_file.write_all(&file.original_length.to_le_bytes())?;
+        header_file.write_u8(1)?;
+        header_file.write_all(&file.hash)?;
+    }
+
+    header_file.write_i32::<LittleEndian>(folder.folders.len() as i32)?;
+    for subfolder in &folder.folders {
+        header_file.write_i32::<LittleEndian>(subfolder.name.len() as i32)?;
+        header_file.write_all(subfolder.name.as_bytes())?;
+        write_folder(subfolder, header_file)?;
+    }
+
+    Ok(())
+}
+
+/// Splits a path into its parent directory and file name.
+fn split_path(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(index) => (&path[..index], &path[index + 1..]),
+        None => ("", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use ed25519_dalek::SigningKey;
+
+    use crate::client::builder::{WorkspaceBuilder, COMPRESSION_THRESHOLD};
+    use crate::client::{parse_header, Compression, SFolder, Workspace};
+
+    /// Test that an archive built with `WorkspaceBuilder` can be re-parsed with `parse_header`,
+    /// and that the resulting tree matches what was staged.
+    #[test]
+    fn test_build_and_reparse() {
+        let dir = std::env::temp_dir();
+        let header_path = dir.join("shaiya-core-test.sah");
+        let data_path = dir.join("shaiya-core-test.saf");
+
+        let mut builder = WorkspaceBuilder::new();
+        builder.add_dir("map").unwrap();
+        builder.add_file("config.ini", b"hello world").unwrap();
+        builder.add_file("map/world.map", b"some map data").unwrap();
+        builder.finish(header_path.to_str().unwrap(), data_path.to_str().unwrap()).unwrap();
+
+        let mut header_file = File::open(&header_path).unwrap();
+        let mut root = SFolder { name: "data".to_owned(), files: vec![], folders: vec![] };
+        parse_header(&mut header_file, &mut root).unwrap();
+
+        assert_eq!(root.files.len(), 1);
+        assert_eq!(root.files[0].name, "config.ini");
+        assert_eq!(root.files[0].length, "hello world".len());
+
+        assert_eq!(root.folders.len(), 1);
+        assert_eq!(root.folders[0].name, "map");
+        assert_eq!(root.folders[0].files.len(), 1);
+        assert_eq!(root.folders[0].files[0].name, "world.map");
+
+        std::fs::remove_file(&header_path).unwrap();
+        std::fs::remove_file(&data_path).unwrap();
+    }
+
+    /// Test that a large file is transparently compressed on write and decompressed on read.
+    #[test]
+    fn test_compression_round_trip() {
+        let dir = std::env::temp_dir();
+        let header_path = dir.join("shaiya-core-test-compressed.sah");
+        let data_path = dir.join("shaiya-core-test-compressed.saf");
+
+        let original = vec![b'a'; COMPRESSION_THRESHOLD * 4];
+
+        let mut builder = WorkspaceBuilder::new();
+        builder.add_file("big.bin", &original).unwrap();
+        builder.finish(header_path.to_str().unwrap(), data_path.to_str().unwrap()).unwrap();
+
+        let workspace = Workspace::from_archive(header_path.to_str().unwrap(), data_path.to_str().unwrap()).unwrap();
+        let file = workspace.file("big.bin").unwrap();
+
+        assert_eq!(file.compression, Compression::Deflate);
+        assert!(file.length < original.len(), "compressed length should be smaller than the original");
+        assert_eq!(file.original_length, original.len(), "original_length should be recoverable without decompressing");
+        assert_eq!(workspace.data(file).unwrap().as_ref(), original.as_slice());
+
+        std::fs::remove_file(&header_path).unwrap();
+        std::fs::remove_file(&data_path).unwrap();
+    }
+
+    /// Test that `Workspace::verify` accepts the correct hash and rejects a tampered one.
+    #[test]
+    fn test_verify_file_hash() {
+        let dir = std::env::temp_dir();
+        let header_path = dir.join("shaiya-core-test-verify.sah");
+        let data_path = dir.join("shaiya-core-test-verify.saf");
+
+        let mut builder = WorkspaceBuilder::new();
+        builder.add_file("config.ini", b"hello world").unwrap();
+        builder.finish(header_path.to_str().unwrap(), data_path.to_str().unwrap()).unwrap();
+
+        let workspace = Workspace::from_archive(header_path.to_str().unwrap(), data_path.to_str().unwrap()).unwrap();
+        let file = workspace.file("config.ini").unwrap();
+
+        let expected_hash = *blake3::hash(b"hello world").as_bytes();
+        assert!(workspace.verify(file, &expected_hash).unwrap());
+        assert!(!workspace.verify(file, &[0u8; 32]).unwrap());
+
+        std::fs::remove_file(&header_path).unwrap();
+        std::fs::remove_file(&data_path).unwrap();
+    }
+
+    /// Test that `WorkspaceBuilder` records a per-file BLAKE3 hash in the header, and that
+    /// `Workspace::verify_stored` uses it to accept genuine data and reject tampered data.
+    #[test]
+    fn test_verify_stored_hash() {
+        let dir = std::env::temp_dir();
+        let header_path = dir.join("shaiya-core-test-verify-stored.sah");
+        let data_path = dir.join("shaiya-core-test-verify-stored.saf");
+
+        let mut builder = WorkspaceBuilder::new();
+        builder.add_file("config.ini", b"hello world").unwrap();
+        builder.finish(header_path.to_str().unwrap(), data_path.to_str().unwrap()).unwrap();
+
+        let workspace = Workspace::from_archive(header_path.to_str().unwrap(), data_path.to_str().unwrap()).unwrap();
+        let file = workspace.file("config.ini").unwrap();
+
+        assert_eq!(file.hash, Some(*blake3::hash(b"hello world").as_bytes()));
+        assert!(workspace.verify_stored(file).unwrap());
+
+        std::fs::remove_file(&header_path).unwrap();
+        std::fs::remove_file(&data_path).unwrap();
+    }
+
+    /// Test that `Workspace::verify_header` accepts a valid detached signature and rejects a
+    /// signature produced by a different key.
+    #[test]
+    fn test_verify_header_signature() {
+        let dir = std::env::temp_dir();
+        let header_path = dir.join("shaiya-core-test-signed.sah");
+        let data_path = dir.join("shaiya-core-test-signed.saf");
+        let signature_path = dir.join("shaiya-core-test-signed.sah.sig");
+
+        let signing_key = SigningKey::generate(&mut rand::rng());
+
+        let mut builder = WorkspaceBuilder::new();
+        builder.add_file("config.ini", b"hello world").unwrap();
+        builder.finish_signed(header_path.to_str().unwrap(), data_path.to_str().unwrap(), &signing_key).unwrap();
+
+        assert!(Workspace::verify_header(header_path.to_str().unwrap(), &signing_key.verifying_key()).unwrap());
+
+        let other_signing_key = SigningKey::generate(&mut rand::rng());
+        assert!(!Workspace::verify_header(header_path.to_str().unwrap(), &other_signing_key.verifying_key()).unwrap());
+
+        std::fs::remove_file(&header_path).unwrap();
+        std::fs::remove_file(&data_path).unwrap();
+        std::fs::remove_file(&signature_path).unwrap();
+    }
+}
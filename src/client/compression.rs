@@ -0,0 +1,89 @@
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use crate::Result;
+
+/// The compression scheme a file's bytes are stored under in the data file. Decompression is
+/// applied after the existing offset/length seek-and-read, so it has no bearing on how an entry is
+/// located - only on how its bytes are interpreted once read.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Compression {
+    /// The file's bytes are stored as-is.
+    None,
+    /// The file's bytes are stored as a raw DEFLATE stream.
+    Deflate,
+    /// The file's bytes are stored as a Zstandard frame.
+    Zstd
+}
+
+impl Compression {
+
+    /// Converts the single-byte flag stored in the header into a `Compression`.
+    ///
+    /// # Arguments
+    /// * `flag` - The flag byte read from the header.
+    pub fn from_flag(flag: u8) -> Result<Compression> {
+        match flag {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Deflate),
+            2 => Ok(Compression::Zstd),
+            _ => Err(format!("Unrecognised compression flag: {}", flag).into())
+        }
+    }
+
+    /// Converts this `Compression` into the single-byte flag stored in the header.
+    pub fn flag(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Deflate => 1,
+            Compression::Zstd => 2
+        }
+    }
+
+    /// Decompresses `data` according to this compression scheme.
+    ///
+    /// # Arguments
+    /// * `data` - The stored (possibly compressed) bytes read from the data file.
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_owned()),
+            Compression::Deflate => {
+                let mut decoder = DeflateDecoder::new(data);
+                let mut decompressed = Vec::new();
+                decoder.read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            },
+            Compression::Zstd => Ok(zstd::decode_all(data)?)
+        }
+    }
+
+    /// Compresses `data` according to this compression scheme.
+    ///
+    /// # Arguments
+    /// * `data` - The original, uncompressed bytes.
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_owned()),
+            Compression::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            },
+            Compression::Zstd => Ok(zstd::encode_all(data, 0)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::Compression;
+
+    /// Test that an unrecognised compression flag is rejected with an error instead of panicking,
+    /// since the flag byte comes directly from a SAH header that could be corrupt or malicious.
+    #[test]
+    fn test_from_flag_rejects_unrecognised_flag() {
+        assert!(Compression::from_flag(0xFF).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use fuser::{
+    Config, Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags, Generation, INodeNo, LockOwner, MountOption,
+    OpenFlags, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, Request
+};
+
+use crate::client::{BlockRead, SFolder, Workspace};
+use crate::Result;
+
+/// How long the kernel is allowed to cache an entry's attributes/name before re-asking us. The
+/// workspace is read-only and never changes underneath a mount, so a generous TTL is safe.
+const ATTR_TTL: Duration = Duration::from_secs(60);
+
+/// The fixed inode number FUSE reserves for the mount's root directory.
+const ROOT_INODE: INodeNo = INodeNo::ROOT;
+
+/// A single entry in the flattened inode table built when the filesystem is mounted.
+enum Entry {
+    Dir { children: Vec<INodeNo> },
+    File { length: u64 }
+}
+
+/// An inode in the flattened tree: its path relative to the workspace root, and what it is.
+struct Inode {
+    path: String,
+    entry: Entry
+}
+
+/// A read-only FUSE filesystem backed by a [`Workspace`]. Directory listings come from
+/// [`SFolder::folders`]/[`SFolder::files`], `getattr` sizes come from a file's original
+/// (decompressed) length, and `read` is served out of a decompressed buffer that's cached on
+/// `open` and evicted on `release`, rather than being re-decompressed from [`Workspace::data`] on
+/// every chunk-sized `read` call.
+pub struct ShaiyaFs<B: BlockRead> {
+    workspace: Workspace<B>,
+    inodes: HashMap<u64, Inode>,
+    /// Decompressed file contents for currently-open file handles, keyed by the handle `open`
+    /// handed out. Populated once per `open` and read from on every subsequent `read`, so a large
+    /// compressed file is only decompressed once per open, not once per page-sized chunk.
+    open_files: Mutex<HashMap<u64, Bytes>>,
+    next_fh: AtomicU64
+}
+
+impl<B: BlockRead> ShaiyaFs<B> {
+
+    /// Builds the flattened inode table for a workspace, ready to be mounted.
+    ///
+    /// # Arguments
+    /// * `workspace` - The workspace to expose as a filesystem.
+    pub fn new(workspace: Workspace<B>) -> ShaiyaFs<B> {
+        let mut inodes = HashMap::new();
+        let mut next_ino = ROOT_INODE.0 + 1;
+        index_folder(workspace.folder("/").unwrap(), "", ROOT_INODE.0, &mut next_ino, &mut inodes);
+        ShaiyaFs { workspace, inodes, open_files: Mutex::new(HashMap::new()), next_fh: AtomicU64::new(1) }
+    }
+
+    /// Looks up the inode for a child of `parent` named `name`, if one exists.
+    fn child_inode(&self, parent: INodeNo, name: &str) -> Option<INodeNo> {
+        let Entry::Dir { children } = &self.inodes.get(&parent.0)?.entry else { return None };
+        children.iter().copied().find(|child| {
+            self.inodes.get(&child.0).is_some_and(|inode| {
+                inode.path.rsplit('/').next().unwrap_or(&inode.path).eq_ignore_ascii_case(name)
+            })
+        })
+    }
+
+    /// Builds the `FileAttr` the kernel expects for a given inode.
+    fn attr(&self, ino: INodeNo, inode: &Inode) -> FileAttr {
+        let (kind, perm, size) = match &inode.entry {
+            Entry::Dir { .. } => (FileType::Directory, 0o555, 0),
+            Entry::File { length } => (FileType::RegularFile, 0o444, *length)
+        };
+
+        FileAttr {
+            ino,
+            size,
+            blocks: size.div_ceil(512),
+            atime: SystemTime::UNIX_EPOCH,
+            mtime: SystemTime::UNIX_EPOCH,
+            ctime: SystemTime::UNIX_EPOCH,
+            crtime: SystemTime::UNIX_EPOCH,
+            kind,
+            perm,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+            blksize: 512,
+            flags: 0
+        }
+    }
+}
+
+/// Recursively flattens a `SFolder` tree into the inode table, assigning sequential inode numbers
+/// as folders are visited.
+fn index_folder(folder: &SFolder, path_prefix: &str, ino: u64, next_ino: &mut u64, inodes: &mut HashMap<u64, Inode>) {
+    let mut children = Vec::with_capacity(folder.files.len() + folder.folders.len());
+
+    for file in &folder.files {
+        let path = join(path_prefix, &file.name);
+        let file_ino = *next_ino;
+        *next_ino += 1;
+
+        inodes.insert(file_ino, Inode { path, entry: Entry::File { length: file.original_length as u64 } });
+        children.push(INodeNo(file_ino));
+    }
+
+    for subfolder in &folder.folders {
+        let path = join(path_prefix, &subfolder.name);
+        let subfolder_ino = *next_ino;
+        *next_ino += 1;
+        children.push(INodeNo(subfolder_ino));
+
+        index_folder(subfolder, &path, subfolder_ino, next_ino, inodes);
+    }
+
+    inodes.insert(ino, Inode { path: path_prefix.to_owned(), entry: Entry::Dir { children } });
+}
+
+/// Joins a path prefix and a name with a `/` separator.
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() { name.to_owned() } else { format!("{}/{}", prefix, name) }
+}
+
+impl<B: BlockRead + Send + Sync + 'static> Filesystem for ShaiyaFs<B> {
+
+    fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        let name = match name.to_str() {
+            Some(name) => name,
+            None => return reply.error(Errno::ENOENT)
+        };
+
+        match self.child_inode(parent, name) {
+            Some(ino) => {
+                let attr = self.attr(ino, &self.inodes[&ino.0]);
+                reply.entry(&ATTR_TTL, &attr, Generation(0))
+            },
+            None => reply.error(Errno::ENOENT)
+        }
+    }
+
+    fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+        match self.inodes.get(&ino.0) {
+            Some(inode) => reply.attr(&ATTR_TTL, &self.attr(ino, inode)),
+            None => reply.error(Errno::ENOENT)
+        }
+    }
+
+    fn open(&self, _req: &Request, ino: INodeNo, _flags: OpenFlags, reply: ReplyOpen) {
+        let path = match self.inodes.get(&ino.0) {
+            Some(Inode { entry: Entry::File { .. }, path }) => path.clone(),
+            _ => return reply.error(Errno::ENOENT)
+        };
+
+        let file = match self.workspace.file(&path) {
+            Ok(file) => file,
+            Err(_) => return reply.error(Errno::ENOENT)
+        };
+
+        // Decompress once up front and cache it under the handle, rather than re-decompressing on
+        // every `read` - the kernel issues one `read` per page-sized chunk, so a naive per-call
+        // decompress would be quadratic in the number of chunks for a large file. `Bytes::freeze`
+        // makes the buffer cheaply cloneable (refcounted), so handing a clone to each `read` call
+        // doesn't re-copy it.
+        let data = match self.workspace.data(file) {
+            Ok(data) => data.freeze(),
+            Err(_) => return reply.error(Errno::EIO)
+        };
+
+        let fh = self.next_fh.fetch_add(1, Ordering::Relaxed);
+        self.open_files.lock().unwrap().insert(fh, data);
+        reply.opened(FileHandle(fh), FopenFlags::empty());
+    }
+
+    fn read(
+        &self, _req: &Request, _ino: INodeNo, fh: FileHandle, offset: u64, size: u32, _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>, reply: ReplyData
+    ) {
+        let data = match self.open_files.lock().unwrap().get(&fh.0) {
+            Some(data) => data.clone(),
+            None => return reply.error(Errno::EBADF)
+        };
+
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(size as usize).min(data.len());
+        reply.data(&data[start..end]);
+    }
+
+    fn release(
+        &self, _req: &Request, _ino: INodeNo, fh: FileHandle, _flags: OpenFlags, _lock_owner: Option<LockOwner>,
+        _flush: bool, reply: ReplyEmpty
+    ) {
+        self.open_files.lock().unwrap().remove(&fh.0);
+        reply.ok();
+    }
+
+    fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
+        let children = match self.inodes.get(&ino.0) {
+            Some(Inode { entry: Entry::Dir { children }, .. }) => children.clone(),
+            _ => return reply.error(Errno::ENOENT)
+        };
+
+        let entries = vec![(ino, FileType::Directory, ".".to_owned()), (ino, FileType::Directory, "..".to_owned())]
+            .into_iter()
+            .chain(children.iter().map(|child| {
+                let inode = &self.inodes[&child.0];
+                let kind = match inode.entry { Entry::Dir { .. } => FileType::Directory, Entry::File { .. } => FileType::RegularFile };
+                let name = inode.path.rsplit('/').next().unwrap_or(&inode.path).to_owned();
+                (*child, kind, name)
+            }));
+
+        for (index, (ino, kind, name)) in entries.enumerate().skip(offset as usize) {
+            if reply.add(ino, (index + 1) as u64, kind, name) {
+                break;
+            }
+        }
+
+        reply.ok();
+    }
+}
+
+/// Mounts a workspace read-only at `mountpoint`, blocking until it is unmounted.
+///
+/// # Arguments
+/// * `workspace`   - The workspace to expose as a filesystem.
+/// * `mountpoint`  - The (existing, empty) directory to mount the filesystem at.
+pub fn mount<B: BlockRead + Send + Sync + 'static>(workspace: Workspace<B>, mountpoint: impl AsRef<Path>) -> Result<()> {
+    let fs = ShaiyaFs::new(workspace);
+    let mut config = Config::default();
+    config.mount_options = vec![MountOption::RO, MountOption::FSName("shaiya".to_owned())];
+    fuser::mount(fs, mountpoint, &config)?;
+    Ok(())
+}
+
+/// Mounts an archive, opened the same way [`Workspace::from_archive`] would, read-only at
+/// `mountpoint`, blocking until it is unmounted.
+///
+/// # Arguments
+/// * `header_file_path`    - The path to the Shaiya Archive Header (usually "data.sah")
+/// * `data_file_path`      - The path to the Shaiya Archive File which contains the file data (usually "data.saf")
+/// * `mountpoint`          - The (existing, empty) directory to mount the filesystem at.
+pub fn mount_archive(header_file_path: &str, data_file_path: &str, mountpoint: impl AsRef<Path>) -> Result<()> {
+    let workspace: Workspace<File> = Workspace::from_archive(header_file_path, data_file_path)?;
+    mount(workspace, mountpoint)
+}
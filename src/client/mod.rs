@@ -3,14 +3,31 @@ use crate::Result;
 use std::io::{Read, Seek, SeekFrom};
 use byteorder::{ReadBytesExt, LittleEndian};
 use bytes::BytesMut;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+mod block_read;
+pub use block_read::BlockRead;
+use block_read::BoundedReader;
+
+mod builder;
+pub use builder::WorkspaceBuilder;
+
+mod compression;
+pub use compression::Compression;
+
+#[cfg(feature = "fuse")]
+pub mod fuse;
 
 /// Represents a valid SAH header.
 const HEADER_MAGIC_VALUE: &str = "SAH";
 
-/// A `workspace` is a collection of the files contained within a folder or archive file.
-pub struct Workspace {
+/// A `workspace` is a collection of the files contained within a folder or archive file. It is
+/// generic over its [`BlockRead`] data source, so the same SAH index can be served from a `File`,
+/// an in-memory buffer, or a memory-mapped file, and concurrent `data()` calls don't contend over
+/// a single seek cursor.
+pub struct Workspace<B: BlockRead = File> {
     root: SFolder,
-    data: File
+    data: B
 }
 
 /// Represents a virtual folder in the workspace. A folder can contain multiple subfolders, and files.
@@ -26,12 +43,26 @@ pub struct SFolder {
 pub struct SFile {
     pub name: String,
     pub offset: usize,
-    pub length: usize
+    /// The stored length, in bytes, of the file's data - i.e. its compressed size if
+    /// `compression` is not [`Compression::None`].
+    pub length: usize,
+    /// The original, decompressed length, in bytes, of the file's data. Equal to `length` when
+    /// `compression` is [`Compression::None`]. Recorded in the header so a file's true size is
+    /// recoverable without reading and decompressing its data.
+    pub original_length: usize,
+    pub compression: Compression,
+    /// The BLAKE3 hash of the file's original (decompressed) bytes, if the header carries one.
+    /// Always `Some` for archives written by the current [`WorkspaceBuilder`]. Like
+    /// [`SFile::original_length`], this field has no version gate, so (as with that field) it can
+    /// only be read correctly from archives written by a `WorkspaceBuilder` that already knew
+    /// about it - not from archives predating this series of format extensions.
+    pub hash: Option<[u8; 32]>
 }
 
-impl Workspace {
+impl Workspace<File> {
 
-    /// Opens a workspace from a header and data file.
+    /// Opens a workspace from a header and data file, using the data file directly (via
+    /// positioned reads) as the `BlockRead` source.
     ///
     /// # Arguments
     /// * `header_file_path`    - The path to the Shaiya Archive Header (usually "data.sah")
@@ -43,7 +74,20 @@ impl Workspace {
         let mut root_folder = SFolder { name: "data".to_owned(), files: vec![], folders: vec![] };
         parse_header(&mut header_file, &mut root_folder)?;
 
-        Ok(Workspace { root: root_folder, data: data_file })
+        Ok(Workspace::from_source(root_folder, data_file))
+    }
+}
+
+impl<B: BlockRead> Workspace<B> {
+
+    /// Creates a workspace from an already-parsed folder tree and a `BlockRead` data source, e.g.
+    /// an in-memory buffer or a memory-mapped file.
+    ///
+    /// # Arguments
+    /// * `root`    - The root folder of the workspace.
+    /// * `data`    - The source the file bytes indexed by `root` are read from.
+    pub fn from_source(root: SFolder, data: B) -> Workspace<B> {
+        Workspace { root, data }
     }
 
     /// Gets a file at a specified path.
@@ -66,7 +110,7 @@ impl Workspace {
             }
         }
 
-        Err(format!("Unable to find file with path: {}", path)).unwrap()
+        panic!("Unable to find file with path: {}", path)
     }
 
     /// Gets a folder at a specified path.
@@ -95,7 +139,7 @@ impl Workspace {
             return Ok(folder)
         }
 
-        Err(format!("Unable to find folder with path: {}", path)).unwrap()
+        panic!("Unable to find folder with path: {}", path)
     }
 
     /// Reads the data for a file.
@@ -103,19 +147,77 @@ impl Workspace {
     /// # Arguments
     /// * `file`    - The file to read.
     pub fn data(&self, file: &SFile) -> Result<BytesMut> {
-        let mut data = &self.data;
-        let required_data = file.offset + file.length;
-        let available_data = data.metadata()?.len() as usize;
+        let required_data = (file.offset + file.length) as u64;
+        let available_data = self.data.len()?;
 
         if required_data > available_data {
-            Err(format!("Required file length exceeds the data available (required: {}, available: {})", required_data, available_data)).unwrap()
+            return Err(format!("Required file length exceeds the data available (required: {}, available: {})", required_data, available_data).into());
         }
 
-        let mut file_buf: Vec<u8> = vec![0; file.length as usize];
-        data.seek(SeekFrom::Start(file.offset as u64))?;
-        data.read_exact(&mut file_buf)?;
+        let mut file_buf: Vec<u8> = vec![0; file.length];
+        self.data.read_at(file.offset as u64, &mut file_buf)?;
+
+        let file_buf = file.compression.decompress(&file_buf)?;
         Ok(BytesMut::from(file_buf.as_slice()))
     }
+
+    /// Returns a bounded, streaming view over a file's bytes, clamped to `[offset, offset+length)`
+    /// of the data source, so callers can stream-copy or parse incrementally instead of buffering
+    /// the whole entry like [`Workspace::data`] does. Note that this streams the file's *stored*
+    /// bytes - if `file.compression` is not [`Compression::None`], the caller is responsible for
+    /// decompressing as it reads.
+    ///
+    /// # Arguments
+    /// * `file`    - The file to read.
+    pub fn reader(&self, file: &SFile) -> impl Read + Seek + '_ {
+        BoundedReader::new(&self.data, file.offset as u64, file.length as u64)
+    }
+
+    /// Verifies a file's integrity by re-hashing the bytes returned by [`Workspace::data`] with
+    /// BLAKE3 and comparing against an expected hash, e.g. one pulled from a known-good manifest.
+    /// This protects servers that load untrusted client-supplied data files.
+    ///
+    /// # Arguments
+    /// * `file`            - The file to verify.
+    /// * `expected_hash`   - The expected BLAKE3 hash of the file's (decompressed) bytes.
+    pub fn verify(&self, file: &SFile, expected_hash: &[u8; 32]) -> Result<bool> {
+        let data = self.data(file)?;
+        Ok(blake3::hash(&data).as_bytes() == expected_hash)
+    }
+
+    /// Verifies a file's integrity against the BLAKE3 hash recorded for it in the header, e.g.
+    /// when loading an untrusted data file alongside a trusted header.
+    ///
+    /// # Arguments
+    /// * `file` - The file to verify.
+    pub fn verify_stored(&self, file: &SFile) -> Result<bool> {
+        match &file.hash {
+            Some(hash) => self.verify(file, hash),
+            None => Err("File has no stored hash to verify against".into())
+        }
+    }
+}
+
+impl Workspace<File> {
+
+    /// Verifies a detached Ed25519 signature over a header file, before trusting any of the
+    /// offsets/lengths it contains. The signature is expected in a sidecar file alongside the
+    /// header, named `<header_file_path>.sig`.
+    ///
+    /// # Arguments
+    /// * `header_file_path`    - The path to the Shaiya Archive Header that was signed.
+    /// * `public_key`          - The Ed25519 public key to verify the signature against.
+    pub fn verify_header(header_file_path: &str, public_key: &VerifyingKey) -> Result<bool> {
+        let mut header_bytes = Vec::new();
+        File::open(header_file_path)?.read_to_end(&mut header_bytes)?;
+
+        let signature_path = format!("{}.sig", header_file_path);
+        let mut signature_bytes = Vec::new();
+        File::open(&signature_path)?.read_to_end(&mut signature_bytes)?;
+        let signature = Signature::from_slice(&signature_bytes)?;
+
+        Ok(public_key.verify(&header_bytes, &signature).is_ok())
+    }
 }
 
 /// Parses a Shaiya header file.
@@ -129,7 +231,7 @@ fn parse_header(header_file: &mut File, folder: &mut SFolder) -> Result<()> {
     header_file.read_exact(&mut header)?;
     let header = std::str::from_utf8(&header)?;
     if header != HEADER_MAGIC_VALUE {
-        Err(format!("Invalid SAH header: {}", header)).unwrap()
+        return Err(format!("Invalid SAH header: {}", header).into());
     }
 
     // Skip the next 4 bytes, read the total file count, and then skip another 45 bytes
@@ -154,7 +256,7 @@ fn parse_folder(header_file: &mut File, folder: &mut SFolder) -> Result<()> {
     for _ in 0..file_qty {
         // Read the name of the file
         let name_len = header_file.read_i32::<LittleEndian>()? as usize;
-        let mut name_data: Vec<u8> = vec![0; name_len as usize];
+        let mut name_data: Vec<u8> = vec![0; name_len];
         header_file.read_exact(name_data.as_mut_slice())?;
         let name = String::from_utf8_lossy(&name_data).trim_end_matches(char::from(0)).to_owned();
 
@@ -168,16 +270,33 @@ fn parse_folder(header_file: &mut File, folder: &mut SFolder) -> Result<()> {
         header_file.read_exact(&mut data)?;
         let length = u32::from_le_bytes(data);
 
-        // Skip the next 4 bytes
-        header_file.seek(SeekFrom::Current(4))?;
-        files.push(SFile { name, offset: offset as usize, length: length as usize});
+        // The first of these 4 bytes is the file's compression flag; real archives leave it at
+        // zero, which reads back as `Compression::None`. The remaining 3 bytes are still unused.
+        let compression = Compression::from_flag(header_file.read_u8()?)?;
+        header_file.seek(SeekFrom::Current(3))?;
+
+        // The original, decompressed length. Equal to `length` for files stored uncompressed.
+        let original_length = header_file.read_i32::<LittleEndian>()? as usize;
+
+        // A 1-byte flag indicating whether a 32-byte BLAKE3 hash of the file's original bytes
+        // follows. Absent for older archives that predate per-file hashing.
+        let has_hash = header_file.read_u8()? != 0;
+        let hash = if has_hash {
+            let mut hash = [0u8; 32];
+            header_file.read_exact(&mut hash)?;
+            Some(hash)
+        } else {
+            None
+        };
+
+        files.push(SFile { name, offset: offset as usize, length: length as usize, original_length, compression, hash });
     }
 
     let folder_qty = header_file.read_i32::<LittleEndian>()?;
     for _ in 0..folder_qty {
         // Read the name of the folder
         let name_len = header_file.read_i32::<LittleEndian>()? as usize;
-        let mut name_data: Vec<u8> = vec![0; name_len as usize];
+        let mut name_data: Vec<u8> = vec![0; name_len];
         header_file.read_exact(name_data.as_mut_slice())?;
         let name = String::from_utf8_lossy(&name_data).trim_end_matches(char::from(0)).to_owned();
 
@@ -188,4 +307,43 @@ fn parse_folder(header_file: &mut File, folder: &mut SFolder) -> Result<()> {
     }
 
     Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use crate::client::{parse_header, Compression, SFile, SFolder, Workspace};
+
+    /// Test that reading a file whose offset/length run past the end of the data source returns an
+    /// error instead of panicking, since a corrupt or malicious header could index past the end of
+    /// the data file.
+    #[test]
+    fn test_data_rejects_out_of_bounds_entry() {
+        let data = Cursor::new(b"hello world".to_vec());
+        let file = SFile { name: "greeting.txt".to_owned(), offset: 6, length: 100, original_length: 100, compression: Compression::None, hash: None };
+        let root = SFolder { name: "data".to_owned(), files: vec![file], folders: vec![] };
+
+        let workspace = Workspace::from_source(root, data);
+        let file = workspace.file("greeting.txt").unwrap();
+        assert!(workspace.data(file).is_err());
+    }
+
+    /// Test that a header file with the wrong magic bytes is rejected with an error instead of
+    /// panicking, since a corrupt or malicious header could start with anything.
+    #[test]
+    fn test_parse_header_rejects_bad_magic() {
+        let dir = std::env::temp_dir();
+        let header_path = dir.join("shaiya-core-test-bad-magic.sah");
+
+        let mut header_file = std::fs::File::create(&header_path).unwrap();
+        header_file.write_all(b"NOT A REAL HEADER").unwrap();
+        drop(header_file);
+
+        let mut header_file = std::fs::File::open(&header_path).unwrap();
+        let mut root = SFolder { name: "data".to_owned(), files: vec![], folders: vec![] };
+        assert!(parse_header(&mut header_file, &mut root).is_err());
+
+        std::fs::remove_file(&header_path).unwrap();
+    }
 }
\ No newline at end of file
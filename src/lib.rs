@@ -1,4 +1,3 @@
-#![feature(seek_convenience)]
 pub mod network;
 pub mod client;
 
@@ -1,6 +1,8 @@
-use rsa::{RSAPublicKey, PublicKeyParts};
-use crate::network::packet::SerializablePacket;
-use bytes::{BytesMut, BufMut};
+use rsa::RsaPublicKey;
+use rsa::traits::PublicKeyParts;
+use crate::network::packet::{DeserializablePacket, SerializablePacket};
+use crate::Result;
+use bytes::{Buf, Bytes, BytesMut, BufMut};
 
 /// The opcode for a login handshake.
 pub const LOGIN_HANDSHAKE_OPCODE: u16 = 0xA101;
@@ -24,13 +26,19 @@ pub struct LoginHandshakeRequest {
 }
 
 /// The method implementation for the login handshake request.
+impl Default for LoginHandshakeRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LoginHandshakeRequest {
 
     /// Initialises a new handshake request.
     pub fn new() -> LoginHandshakeRequest {
         LoginHandshakeRequest {
             opcode: LOGIN_HANDSHAKE_OPCODE,
-            encrypted: false,
+            encrypted: true,
             exponent_length: EXPONENT_LENGTH,
             modulus_length: MODULUS_LENGTH,
             exponent: [0; EXPONENT_LENGTH as usize],
@@ -42,7 +50,7 @@ impl LoginHandshakeRequest {
     ///
     /// # Arguments
     /// * `public_key` - The 1024-bit RSA public key to send in the handshake request.
-    pub fn from_key(public_key: &RSAPublicKey) -> LoginHandshakeRequest {
+    pub fn from_key(public_key: &RsaPublicKey) -> LoginHandshakeRequest {
         let e = public_key.e().to_bytes_le();
         let n = public_key.n().to_bytes_le();
         assert_eq!(n.len(), MODULUS_LENGTH as usize);
@@ -52,7 +60,7 @@ impl LoginHandshakeRequest {
         request.modulus_length = n.len() as u8;
         request.exponent[..e.len()].clone_from_slice(e.as_slice());
         request.modulus[..n.len()].clone_from_slice(n.as_slice());
-        return request;
+        request
     }
 }
 
@@ -68,26 +76,81 @@ impl SerializablePacket for LoginHandshakeRequest {
     }
 }
 
+/// Add support for deserializing this packet from the network. The leading opcode is assumed to
+/// have already been consumed by the caller, e.g. a `PacketRegistry`.
+impl DeserializablePacket for LoginHandshakeRequest {
+    fn deserialize(reader: &mut Bytes) -> Result<LoginHandshakeRequest> {
+        let encrypted = reader.try_get_u8()? != 0;
+        let exponent_length = reader.try_get_u8()?;
+        let modulus_length = reader.try_get_u8()?;
+
+        let mut exponent = [0; EXPONENT_LENGTH as usize];
+        reader.try_copy_to_slice(&mut exponent)?;
+
+        let mut modulus = [0; MODULUS_LENGTH as usize];
+        reader.try_copy_to_slice(&mut modulus)?;
+
+        Ok(LoginHandshakeRequest {
+            opcode: LOGIN_HANDSHAKE_OPCODE,
+            encrypted,
+            exponent_length,
+            modulus_length,
+            exponent,
+            modulus
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::network::packet::LoginHandshakeRequest;
+    use crate::network::packet::{DeserializablePacket, LoginHandshakeRequest, SerializablePacket};
     use core::mem;
+    use bytes::{Bytes, BytesMut};
     use crate::network::packet::login_handshake::{LOGIN_HANDSHAKE_OPCODE, EXPONENT_LENGTH, MODULUS_LENGTH};
 
-    /// Test the length of the handshake packet.
+    /// Test the length of the handshake packet. The struct's fields sum to 197 bytes, but the
+    /// leading `u16` forces the compiler to round the overall size up to an even number.
     #[test]
     fn test_packet_length() {
-        assert_eq!(mem::size_of::<LoginHandshakeRequest>(), 197);
+        assert_eq!(mem::size_of::<LoginHandshakeRequest>(), 198);
     }
 
-    /// Test that the opcode and public key sizes are correct in a default-initialised struct.
+    /// Test that the opcode and public key sizes are correct in a default-initialised struct, and
+    /// that it advertises encryption, since packet bodies are in fact AES-encrypted once the
+    /// handshake completes.
     #[test]
-    #[allow(safe_packed_borrows)]
     fn test_default_values() {
         let request = LoginHandshakeRequest::new();
         assert_eq!(request.opcode, LOGIN_HANDSHAKE_OPCODE);
         assert_eq!(request.exponent_length, EXPONENT_LENGTH);
         assert_eq!(request.modulus_length, MODULUS_LENGTH);
+        assert!(request.encrypted);
     }
 
+    /// Test that a handshake request can be deserialized back out of its serialized form.
+    #[test]
+    fn test_deserialize_round_trip() {
+        let request = LoginHandshakeRequest::new();
+
+        let mut writer = BytesMut::new();
+        request.serialize(&mut writer);
+
+        // Skip the opcode, as a `PacketRegistry` would have already consumed it to dispatch here.
+        let mut reader = writer.freeze().split_off(2);
+        let deserialized = LoginHandshakeRequest::deserialize(&mut reader).unwrap();
+
+        assert_eq!(deserialized.opcode, LOGIN_HANDSHAKE_OPCODE);
+        assert_eq!(deserialized.exponent_length, EXPONENT_LENGTH);
+        assert_eq!(deserialized.modulus_length, MODULUS_LENGTH);
+        assert_eq!(deserialized.exponent, request.exponent);
+        assert_eq!(deserialized.modulus, request.modulus);
+    }
+
+    /// Test that a truncated buffer is rejected with an error instead of panicking, since this is
+    /// reached directly from an inbound, potentially short or malformed frame.
+    #[test]
+    fn test_deserialize_rejects_truncated_buffer() {
+        let mut reader = Bytes::from_static(&[1, 2, 3]);
+        assert!(LoginHandshakeRequest::deserialize(&mut reader).is_err());
+    }
 }
\ No newline at end of file
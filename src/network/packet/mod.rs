@@ -1,10 +1,103 @@
 mod login_handshake;
 pub use login_handshake::LoginHandshakeRequest;
-use bytes::BytesMut;
+use bytes::{Buf, Bytes, BytesMut};
+use std::any::Any;
+use std::collections::HashMap;
+
+use crate::Result;
 
 /// A trait that a structure must implement if it wishes to be treated as a serializable packet.
 pub trait SerializablePacket {
 
     /// Serializes the structure into a slice of bytes.
     fn serialize(&self, writer: &mut BytesMut);
-}
\ No newline at end of file
+}
+
+/// A trait that a structure must implement if it wishes to be treated as a deserializable packet.
+pub trait DeserializablePacket: Sized {
+
+    /// Deserializes the structure from a buffer. The leading opcode is assumed to have already
+    /// been consumed by the caller (e.g. a [`PacketRegistry`]).
+    fn deserialize(reader: &mut Bytes) -> Result<Self>;
+}
+
+/// A decoder that deserializes a packet of a specific type, then erases it to a `Box<dyn Any>` so
+/// that decoders of differing packet types can be stored in the same [`PacketRegistry`].
+type PacketDecoder = Box<dyn Fn(&mut Bytes) -> Result<Box<dyn Any>> + Send + Sync>;
+
+/// Maps packet opcodes to the decoder for the packet type they represent, so a server loop can
+/// read a framed buffer, peek its leading little-endian `u16` opcode (e.g.
+/// [`LOGIN_HANDSHAKE_OPCODE`](login_handshake::LOGIN_HANDSHAKE_OPCODE)), and dispatch to the right
+/// struct without the caller needing to match on opcodes itself.
+#[derive(Default)]
+pub struct PacketRegistry {
+    decoders: HashMap<u16, PacketDecoder>
+}
+
+impl PacketRegistry {
+
+    /// Creates an empty registry.
+    pub fn new() -> PacketRegistry {
+        PacketRegistry { decoders: HashMap::new() }
+    }
+
+    /// Registers the decoder for a packet type under its opcode.
+    ///
+    /// # Arguments
+    /// * `opcode` - The opcode that identifies packets of this type on the wire.
+    pub fn register<P>(&mut self, opcode: u16) where P: DeserializablePacket + 'static {
+        self.decoders.insert(opcode, Box::new(|reader: &mut Bytes| {
+            let packet = P::deserialize(reader)?;
+            Ok(Box::new(packet) as Box<dyn Any>)
+        }));
+    }
+
+    /// Reads the leading little-endian opcode from `buffer` and dispatches to the decoder
+    /// registered for it, returning the decoded packet as a type-erased `Box<dyn Any>` for the
+    /// caller to `downcast_ref`/`downcast` into the concrete packet type.
+    ///
+    /// # Arguments
+    /// * `buffer` - A framed buffer, positioned at the start of a packet's opcode.
+    pub fn decode(&self, buffer: &mut Bytes) -> Result<Box<dyn Any>> {
+        let opcode = buffer.try_get_u16_le()?;
+        let decoder = self.decoders.get(&opcode)
+            .ok_or_else(|| format!("No decoder registered for opcode: {:#06x}", opcode))?;
+        decoder(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, BytesMut};
+
+    use super::PacketRegistry;
+    use crate::network::packet::login_handshake::LOGIN_HANDSHAKE_OPCODE;
+    use crate::network::packet::{LoginHandshakeRequest, SerializablePacket};
+
+    /// Test that a registered decoder is dispatched to by opcode, and the decoded packet can be
+    /// downcast back into its concrete type.
+    #[test]
+    fn test_register_and_decode() {
+        let mut registry = PacketRegistry::new();
+        registry.register::<LoginHandshakeRequest>(LOGIN_HANDSHAKE_OPCODE);
+
+        let mut writer = BytesMut::new();
+        LoginHandshakeRequest::new().serialize(&mut writer);
+
+        let mut buffer = writer.freeze();
+        let decoded = registry.decode(&mut buffer).unwrap();
+        assert!(decoded.downcast_ref::<LoginHandshakeRequest>().is_some());
+    }
+
+    /// Test that an opcode with no registered decoder is rejected with an error.
+    #[test]
+    fn test_decode_rejects_unknown_opcode() {
+        let registry = PacketRegistry::new();
+
+        let mut buffer = BytesMut::new();
+        buffer.put_u16_le(0xFFFF);
+
+        let mut buffer = buffer.freeze();
+        assert!(registry.decode(&mut buffer).is_err());
+    }
+}